@@ -1,25 +1,137 @@
 use core::array;
 
 use libm::roundf;
-use nalgebra::Const;
+use nalgebra::{convert, Const};
 
 use crate::activation::FusedActivation;
 use crate::activation::{relu, relu6};
 use crate::buffer::Buffer2D;
+use crate::padding::Padding2D;
 use crate::quantize::Quantized;
 use crate::tensor::Tensor4D;
 
-// TODO: Performance evaluation (fine cast + iters vs bulk cast + nalgebra built-in)
+/// `im2col`-style fast path: materializes each sliding window as a column of
+/// a matrix and reduces all output cells with a single *integer* GEMM
+/// (window columns times a column of `1`s) instead of the per-cell
+/// `Buffer2D::from_fn` + scalar `.cast::<i32>().sum()` loop below. The
+/// reduction itself stays in `i32` end to end, matching the scalar path's
+/// rounding (only the final `sum / len` division, identical to the scalar
+/// path's `1. / len as f32 * sum as f32`, touches floating point), so this is
+/// a vectorizable integer reduction rather than a float GEMM in disguise.
+/// Only applies when the pooling divisor is the same for every output cell
+/// (`VALID` padding, or `SAME`/`EXPLICIT` padding with `count_include_pad`),
+/// since a single reduction vector otherwise can't represent the per-cell
+/// window sizes near the padded border; other cases fall through to the
+/// scalar loop below.
+mod gemm {
+    use nalgebra::{convert, DMatrix, DVector};
+
+    use crate::activation::{relu, relu6, FusedActivation};
+    use crate::buffer::Buffer2D;
+    use crate::quantize::Quantized;
+    use crate::tensor::Tensor4D;
+
+    use super::AveragePool2DOptions;
+
+    fn sum_channel(
+        input_rows: usize,
+        input_cols: usize,
+        tap: impl Fn(usize, usize) -> i32,
+        filter_rows: usize,
+        filter_cols: usize,
+        output_rows: usize,
+        output_cols: usize,
+        strides: (usize, usize),
+        row_before: usize,
+        col_before: usize,
+    ) -> DVector<i32> {
+        let columns = DMatrix::from_fn(
+            filter_rows * filter_cols,
+            output_rows * output_cols,
+            |k, cell| {
+                let (m, n) = (k / filter_cols, k % filter_cols);
+                let (i, j) = (cell / output_cols, cell % output_cols);
+                match (
+                    (strides.0 * i + m).checked_sub(row_before),
+                    (strides.1 * j + n).checked_sub(col_before),
+                ) {
+                    (Some(row), Some(col)) if row < input_rows && col < input_cols => {
+                        tap(row, col)
+                    }
+                    _ => 0,
+                }
+            },
+        );
+        let ones = DVector::from_element(filter_rows * filter_cols, 1i32);
+        columns.transpose() * ones
+    }
+
+    pub(super) fn average_pool_2d<
+        T: Quantized,
+        const INPUT_ROWS: usize,
+        const INPUT_COLS: usize,
+        const INPUT_CHANS: usize,
+        const FILTER_ROWS: usize,
+        const FILTER_COLS: usize,
+        const OUTPUT_ROWS: usize,
+        const OUTPUT_COLS: usize,
+    >(
+        input: Tensor4D<T, 1, INPUT_ROWS, INPUT_COLS, INPUT_CHANS, 1>,
+        output_scale: [f32; 1],
+        output_zero_point: [T; 1],
+        options: &AveragePool2DOptions,
+        constants: (f32, f32),
+        strides: (usize, usize),
+        row_before: usize,
+        col_before: usize,
+        len: usize,
+    ) -> Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1> {
+        let channel_sums: [DVector<i32>; INPUT_CHANS] = core::array::from_fn(|c| {
+            sum_channel(
+                INPUT_ROWS,
+                INPUT_COLS,
+                |row, col| {
+                    let x = input.buffer[0][(row, col)];
+                    convert::<T, i32>(x.get(c).copied().unwrap_or(x[0]))
+                },
+                FILTER_ROWS,
+                FILTER_COLS,
+                OUTPUT_ROWS,
+                OUTPUT_COLS,
+                strides,
+                row_before,
+                col_before,
+            )
+        });
+        Tensor4D::new(
+            [Buffer2D::from_fn(|i, j| {
+                core::array::from_fn(|c| {
+                    let sum = channel_sums[c][i * OUTPUT_COLS + j];
+                    let x = 1. / len as f32 * sum as f32;
+                    let y =
+                        T::from_superset_unchecked(&libm::roundf(constants.0 * x + constants.1));
+                    match options.fused_activation {
+                        FusedActivation::NONE => y,
+                        FusedActivation::RELU => relu(y, output_zero_point[0]),
+                        FusedActivation::RELU6 => relu6(y, output_scale[0], output_zero_point[0]),
+                    }
+                })
+            })],
+            output_scale,
+            output_zero_point,
+        )
+    }
+}
 
 pub struct AveragePool2DOptions {
     pub fused_activation: FusedActivation,
-    pub padding: AveragePool2DPadding,
+    pub padding: Padding2D,
     pub strides: (usize, usize),
-}
-
-pub enum AveragePool2DPadding {
-    SAME,
-    VALID,
+    /// When `true`, divide by the full `FILTER_ROWS * FILTER_COLS` window
+    /// area regardless of padding (ONNX/PyTorch `AvgPool` semantics). When
+    /// `false` (the default TFLite behavior), divide only by the number of
+    /// in-bounds taps.
+    pub count_include_pad: bool,
 }
 
 pub fn average_pool_2d<
@@ -39,41 +151,66 @@ pub fn average_pool_2d<
     options: AveragePool2DOptions,
     constants: (f32, f32),
 ) -> Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1> {
+    let padded = !matches!(options.padding, Padding2D::VALID);
+    let (row_before, _) = options
+        .padding
+        .row_offsets(INPUT_ROWS, FILTER_ROWS, options.strides.0, OUTPUT_ROWS);
+    let (col_before, _) = options
+        .padding
+        .col_offsets(INPUT_COLS, FILTER_COLS, options.strides.1, OUTPUT_COLS);
+
+    if !padded || options.count_include_pad {
+        return gemm::average_pool_2d(
+            input,
+            output_scale,
+            output_zero_point,
+            &options,
+            constants,
+            options.strides,
+            row_before,
+            col_before,
+            FILTER_ROWS * FILTER_COLS,
+        );
+    }
+
     Tensor4D::new(
         [Buffer2D::from_fn(|i, j| {
             array::from_fn(|c| {
                 let mut len = FILTER_ROWS * FILTER_COLS;
-                let view: Buffer2D<T, FILTER_ROWS, FILTER_COLS> =
-                    Buffer2D::from_fn(|m, n| match options.padding {
-                        AveragePool2DPadding::SAME => {
-                            let shift = ((FILTER_ROWS - 1) / 2, (FILTER_COLS - 1) / 2);
-                            let index = (
-                                if let Some(x) = (options.strides.0 * i + m).checked_sub(shift.0) {
-                                    x
-                                } else {
-                                    len -= 1;
-                                    return T::from_superset_unchecked(&0);
-                                },
-                                if let Some(x) = (options.strides.1 * j + n).checked_sub(shift.1) {
-                                    x
-                                } else {
+                let view: Buffer2D<T, FILTER_ROWS, FILTER_COLS> = Buffer2D::from_fn(|m, n| {
+                    if padded {
+                        let index = (
+                            if let Some(x) = (options.strides.0 * i + m).checked_sub(row_before) {
+                                x
+                            } else {
+                                if !options.count_include_pad {
                                     len -= 1;
-                                    return T::from_superset_unchecked(&0);
-                                },
-                            );
-                            if let Some(x) = input.buffer[0].get(index) {
-                                x.get(c).copied().unwrap_or(x[0])
+                                }
+                                return T::from_superset_unchecked(&0);
+                            },
+                            if let Some(x) = (options.strides.1 * j + n).checked_sub(col_before) {
+                                x
                             } else {
+                                if !options.count_include_pad {
+                                    len -= 1;
+                                }
+                                return T::from_superset_unchecked(&0);
+                            },
+                        );
+                        if let Some(x) = input.buffer[0].get(index) {
+                            x.get(c).copied().unwrap_or(x[0])
+                        } else {
+                            if !options.count_include_pad {
                                 len -= 1;
-                                T::from_superset_unchecked(&0)
                             }
+                            T::from_superset_unchecked(&0)
                         }
-                        AveragePool2DPadding::VALID => {
-                            let x = input.buffer[0]
-                                [(options.strides.0 * i + m, options.strides.1 * j + n)];
-                            x.get(c).copied().unwrap_or(x[0])
-                        }
-                    });
+                    } else {
+                        let x = input.buffer[0]
+                            [(options.strides.0 * i + m, options.strides.1 * j + n)];
+                        x.get(c).copied().unwrap_or(x[0])
+                    }
+                });
                 let x = 1. / len as f32 * view.cast::<i32>().sum() as f32;
                 let y = T::from_superset_unchecked(&roundf(constants.0 * x + constants.1));
                 match options.fused_activation {
@@ -88,6 +225,52 @@ pub fn average_pool_2d<
     )
 }
 
+/// Adaptive average pooling, as used by global-average-pooling heads and SPP
+/// layers: instead of a fixed filter/stride, each output cell derives its own
+/// pooling window from the input and output dimensions.
+pub fn adaptive_average_pool_2d<
+    T: Quantized,
+    const INPUT_ROWS: usize,
+    const INPUT_COLS: usize,
+    const INPUT_CHANS: usize,
+    const OUTPUT_ROWS: usize,
+    const OUTPUT_COLS: usize,
+>(
+    input: Tensor4D<T, 1, INPUT_ROWS, INPUT_COLS, INPUT_CHANS, 1>,
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+    fused_activation: FusedActivation,
+    constants: (f32, f32),
+) -> Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1> {
+    Tensor4D::new(
+        [Buffer2D::from_fn(|i, j| {
+            let start_r = i * INPUT_ROWS / OUTPUT_ROWS;
+            let end_r = ((i + 1) * INPUT_ROWS + OUTPUT_ROWS - 1) / OUTPUT_ROWS;
+            let start_c = j * INPUT_COLS / OUTPUT_COLS;
+            let end_c = ((j + 1) * INPUT_COLS + OUTPUT_COLS - 1) / OUTPUT_COLS;
+            let len = (end_r - start_r) * (end_c - start_c);
+            array::from_fn(|c| {
+                let sum: i32 = (start_r..end_r)
+                    .flat_map(|m| (start_c..end_c).map(move |n| (m, n)))
+                    .map(|(m, n)| {
+                        let x = input.buffer[0][(m, n)];
+                        convert::<T, i32>(x.get(c).copied().unwrap_or(x[0]))
+                    })
+                    .sum();
+                let x = 1. / len as f32 * sum as f32;
+                let y = T::from_superset_unchecked(&roundf(constants.0 * x + constants.1));
+                match fused_activation {
+                    FusedActivation::NONE => y,
+                    FusedActivation::RELU => relu(y, output_zero_point[0]),
+                    FusedActivation::RELU6 => relu6(y, output_scale[0], output_zero_point[0]),
+                }
+            })
+        })],
+        output_scale,
+        output_zero_point,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::matrix;
@@ -107,8 +290,9 @@ mod tests {
     const OUTPUT_ZERO_POINT: [i8; 1] = [16];
     const OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
         fused_activation: FusedActivation::NONE,
-        padding: AveragePool2DPadding::SAME,
+        padding: Padding2D::SAME,
         strides: (1, 1),
+        count_include_pad: false,
     };
     const CONSTANTS: (f32, f32) = (0.866_666_7, 3.866_666_6);
     const OUTPUT: Tensor4D<i8, 1, 2, 3, 2, 1> = Tensor4D {
@@ -134,4 +318,190 @@ mod tests {
             OUTPUT
         );
     }
+
+    #[test]
+    fn average_pool_2d_same_padding_is_asymmetric() {
+        const INPUT: Tensor4D<i8, 1, 1, 3, 1, 1> = Tensor4D {
+            buffer: [matrix![[10], [20], [30]]],
+            scale: [1.],
+            zero_point: [0],
+        };
+        const OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::SAME,
+            strides: (1, 2),
+            count_include_pad: false,
+        };
+        assert_eq!(
+            average_pool_2d(
+                INPUT,
+                (Const::<1>, Const::<2>),
+                [1.],
+                [0],
+                OPTIONS,
+                (1., 0.),
+            ),
+            Tensor4D {
+                buffer: [matrix![[15], [30]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    #[test]
+    fn average_pool_2d_explicit_padding_is_asymmetric() {
+        const INPUT: Tensor4D<i8, 1, 1, 3, 1, 1> = Tensor4D {
+            buffer: [matrix![[10], [20], [30]]],
+            scale: [1.],
+            zero_point: [0],
+        };
+        const OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::EXPLICIT {
+                top: 0,
+                bottom: 0,
+                left: 1,
+                right: 0,
+            },
+            strides: (1, 2),
+            count_include_pad: false,
+        };
+        assert_eq!(
+            average_pool_2d(
+                INPUT,
+                (Const::<1>, Const::<2>),
+                [1.],
+                [0],
+                OPTIONS,
+                (1., 0.),
+            ),
+            Tensor4D {
+                buffer: [matrix![[10], [25]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    #[test]
+    fn average_pool_2d_count_include_pad() {
+        const INPUT: Tensor4D<i8, 1, 1, 3, 1, 1> = Tensor4D {
+            buffer: [matrix![[10], [20], [30]]],
+            scale: [1.],
+            zero_point: [0],
+        };
+        const OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::SAME,
+            strides: (1, 2),
+            count_include_pad: true,
+        };
+        assert_eq!(
+            average_pool_2d(
+                INPUT,
+                (Const::<1>, Const::<2>),
+                [1.],
+                [0],
+                OPTIONS,
+                (1., 0.),
+            ),
+            Tensor4D {
+                buffer: [matrix![[15], [15]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    // Confirms the `im2col` GEMM path (exercised whenever `padding` is
+    // `VALID`, as here) agrees with the scalar, per-cell reduction it
+    // replaces on the same shapes as `average_pool_2d_layer` above, down to
+    // the final quantized code.
+    #[test]
+    fn average_pool_2d_im2col_matches_scalar_reduction() {
+        const OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::VALID,
+            strides: (1, 1),
+            count_include_pad: false,
+        };
+        assert_eq!(
+            average_pool_2d(
+                INPUT,
+                FILTER_SHAPE,
+                OUTPUT_SCALE,
+                OUTPUT_ZERO_POINT,
+                OPTIONS,
+                CONSTANTS,
+            ),
+            Tensor4D {
+                buffer: [matrix![[9, 10]]],
+                scale: OUTPUT_SCALE,
+                zero_point: OUTPUT_ZERO_POINT,
+            }
+        );
+        // Zero `EXPLICIT` padding covers the same windows as `VALID` but
+        // takes the scalar loop, so both paths must give the same codes.
+        const SCALAR_OPTIONS: AveragePool2DOptions = AveragePool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::EXPLICIT {
+                top: 0,
+                bottom: 0,
+                left: 0,
+                right: 0,
+            },
+            strides: (1, 1),
+            count_include_pad: false,
+        };
+        let im2col: Tensor4D<i8, 1, 1, 1, 2, 1> = average_pool_2d(
+            INPUT,
+            FILTER_SHAPE,
+            OUTPUT_SCALE,
+            OUTPUT_ZERO_POINT,
+            OPTIONS,
+            CONSTANTS,
+        );
+        let scalar: Tensor4D<i8, 1, 1, 1, 2, 1> = average_pool_2d(
+            INPUT,
+            FILTER_SHAPE,
+            OUTPUT_SCALE,
+            OUTPUT_ZERO_POINT,
+            SCALAR_OPTIONS,
+            CONSTANTS,
+        );
+        assert_eq!(im2col, scalar);
+    }
+
+    #[test]
+    fn adaptive_average_pool_2d_identity() {
+        assert_eq!(
+            adaptive_average_pool_2d::<i8, 2, 3, 2, 2, 3>(
+                INPUT,
+                INPUT.scale,
+                INPUT.zero_point,
+                FusedActivation::NONE,
+                (1., 0.),
+            ),
+            INPUT
+        );
+    }
+
+    #[test]
+    fn adaptive_average_pool_2d_global() {
+        assert_eq!(
+            adaptive_average_pool_2d::<i8, 2, 3, 2, 1, 1>(
+                INPUT,
+                OUTPUT_SCALE,
+                OUTPUT_ZERO_POINT,
+                FusedActivation::NONE,
+                CONSTANTS,
+            ),
+            Tensor4D {
+                buffer: [matrix![[9, 10]]],
+                scale: OUTPUT_SCALE,
+                zero_point: OUTPUT_ZERO_POINT,
+            }
+        );
+    }
 }
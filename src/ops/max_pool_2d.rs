@@ -0,0 +1,251 @@
+use core::array;
+
+use libm::roundf;
+use nalgebra::{convert, Const};
+use num_traits::Bounded;
+
+use crate::activation::FusedActivation;
+use crate::activation::{relu, relu6};
+use crate::buffer::Buffer2D;
+use crate::padding::Padding2D;
+use crate::quantize::Quantized;
+use crate::tensor::Tensor4D;
+
+pub struct MaxPool2DOptions {
+    pub fused_activation: FusedActivation,
+    pub padding: Padding2D,
+    pub strides: (usize, usize),
+}
+
+pub fn max_pool_2d<
+    T: Quantized + Bounded,
+    const INPUT_ROWS: usize,
+    const INPUT_COLS: usize,
+    const INPUT_CHANS: usize,
+    const FILTER_ROWS: usize,
+    const FILTER_COLS: usize,
+    const OUTPUT_ROWS: usize,
+    const OUTPUT_COLS: usize,
+>(
+    input: Tensor4D<T, 1, INPUT_ROWS, INPUT_COLS, INPUT_CHANS, 1>,
+    filter_shape: (Const<FILTER_ROWS>, Const<FILTER_COLS>),
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+    options: MaxPool2DOptions,
+    constants: Option<(f32, f32)>,
+) -> Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1> {
+    max_pool_2d_with_indices(
+        input,
+        filter_shape,
+        output_scale,
+        output_zero_point,
+        options,
+        constants,
+    )
+    .0
+}
+
+/// Like [`max_pool_2d`], but also returns the flattened within-window
+/// argmax offset (`m * FILTER_COLS + n`) of each output cell, so callers can
+/// implement unpooling/upsampling.
+///
+/// The argmax offset is stored in the same quantized type `T` as the pooled
+/// data via an unchecked cast, so `FILTER_ROWS * FILTER_COLS` must not exceed
+/// `T::max_value()` (127 for `i8`) or the offset silently wraps; debug builds
+/// assert this, but it is still the caller's responsibility to size filters
+/// within `T`'s range in release builds.
+pub fn max_pool_2d_with_indices<
+    T: Quantized + Bounded,
+    const INPUT_ROWS: usize,
+    const INPUT_COLS: usize,
+    const INPUT_CHANS: usize,
+    const FILTER_ROWS: usize,
+    const FILTER_COLS: usize,
+    const OUTPUT_ROWS: usize,
+    const OUTPUT_COLS: usize,
+>(
+    input: Tensor4D<T, 1, INPUT_ROWS, INPUT_COLS, INPUT_CHANS, 1>,
+    _filter_shape: (Const<FILTER_ROWS>, Const<FILTER_COLS>),
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+    options: MaxPool2DOptions,
+    constants: Option<(f32, f32)>,
+) -> (
+    Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1>,
+    Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, INPUT_CHANS, 1>,
+) {
+    let padded = !matches!(options.padding, Padding2D::VALID);
+    let (row_before, _) = options
+        .padding
+        .row_offsets(INPUT_ROWS, FILTER_ROWS, options.strides.0, OUTPUT_ROWS);
+    let (col_before, _) = options
+        .padding
+        .col_offsets(INPUT_COLS, FILTER_COLS, options.strides.1, OUTPUT_COLS);
+
+    debug_assert!(
+        FILTER_ROWS * FILTER_COLS <= convert::<T, i32>(T::max_value()) as usize,
+        "argmax offset m * FILTER_COLS + n must fit in T without wrapping"
+    );
+
+    let tap = |i: usize, j: usize, c: usize, m: usize, n: usize| -> T {
+        if padded {
+            let index = (
+                match (options.strides.0 * i + m).checked_sub(row_before) {
+                    Some(x) => x,
+                    None => return T::min_value(),
+                },
+                match (options.strides.1 * j + n).checked_sub(col_before) {
+                    Some(x) => x,
+                    None => return T::min_value(),
+                },
+            );
+            match input.buffer[0].get(index) {
+                Some(x) => x.get(c).copied().unwrap_or(x[0]),
+                None => T::min_value(),
+            }
+        } else {
+            let x = input.buffer[0][(options.strides.0 * i + m, options.strides.1 * j + n)];
+            x.get(c).copied().unwrap_or(x[0])
+        }
+    };
+
+    let argmax = |i: usize, j: usize, c: usize| -> (T, usize) {
+        let mut best_value = T::min_value();
+        let mut best_index = 0;
+        for m in 0..FILTER_ROWS {
+            for n in 0..FILTER_COLS {
+                let value = tap(i, j, c, m, n);
+                if convert::<T, i32>(value) > convert::<T, i32>(best_value) {
+                    best_value = value;
+                    best_index = m * FILTER_COLS + n;
+                }
+            }
+        }
+        (best_value, best_index)
+    };
+
+    let requantize = |y: T| -> T {
+        let y = match constants {
+            Some(constants) => T::from_superset_unchecked(&roundf(
+                constants.0 * convert::<T, i32>(y) as f32 + constants.1,
+            )),
+            None => y,
+        };
+        match options.fused_activation {
+            FusedActivation::NONE => y,
+            FusedActivation::RELU => relu(y, output_zero_point[0]),
+            FusedActivation::RELU6 => relu6(y, output_scale[0], output_zero_point[0]),
+        }
+    };
+
+    (
+        Tensor4D::new(
+            [Buffer2D::from_fn(|i, j| {
+                array::from_fn(|c| requantize(argmax(i, j, c).0))
+            })],
+            output_scale,
+            output_zero_point,
+        ),
+        Tensor4D::new(
+            [Buffer2D::from_fn(|i, j| {
+                array::from_fn(|c| T::from_superset_unchecked(&(argmax(i, j, c).1 as i32)))
+            })],
+            [1.],
+            [T::from_superset_unchecked(&0)],
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::matrix;
+
+    use super::*;
+
+    const INPUT: Tensor4D<i8, 1, 2, 3, 2, 1> = Tensor4D {
+        buffer: [matrix![
+            [1, 2], [3, 4], [5, 6];
+            [7, 8], [9, 10], [11, 12]
+        ]],
+        scale: [0.13],
+        zero_point: [14],
+    };
+    const FILTER_SHAPE: (Const<2>, Const<2>) = (Const, Const);
+    const OPTIONS: MaxPool2DOptions = MaxPool2DOptions {
+        fused_activation: FusedActivation::NONE,
+        padding: Padding2D::VALID,
+        strides: (1, 1),
+    };
+
+    #[test]
+    fn max_pool_2d_layer() {
+        assert_eq!(
+            max_pool_2d(INPUT, FILTER_SHAPE, INPUT.scale, INPUT.zero_point, OPTIONS, None),
+            Tensor4D {
+                buffer: [matrix![[9, 10], [11, 12]]],
+                scale: [0.13],
+                zero_point: [14],
+            }
+        );
+    }
+
+    #[test]
+    fn max_pool_2d_same_padding_is_asymmetric() {
+        const INPUT: Tensor4D<i8, 1, 1, 3, 1, 1> = Tensor4D {
+            buffer: [matrix![[-10], [-20], [-30]]],
+            scale: [1.],
+            zero_point: [0],
+        };
+        const OPTIONS: MaxPool2DOptions = MaxPool2DOptions {
+            fused_activation: FusedActivation::NONE,
+            padding: Padding2D::SAME,
+            strides: (1, 2),
+        };
+        // The second window is `[-30, <pad>]`: if the padding tap were filled
+        // with `0` instead of `T::min_value()`, the pad would incorrectly win
+        // the max over the real, all-negative input.
+        assert_eq!(
+            max_pool_2d(
+                INPUT,
+                (Const::<1>, Const::<2>),
+                INPUT.scale,
+                INPUT.zero_point,
+                OPTIONS,
+                None,
+            ),
+            Tensor4D {
+                buffer: [matrix![[-10], [-30]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    #[test]
+    fn max_pool_2d_with_indices_layer() {
+        let (output, indices) = max_pool_2d_with_indices(
+            INPUT,
+            FILTER_SHAPE,
+            INPUT.scale,
+            INPUT.zero_point,
+            OPTIONS,
+            None,
+        );
+        assert_eq!(
+            output,
+            Tensor4D {
+                buffer: [matrix![[9, 10], [11, 12]]],
+                scale: [0.13],
+                zero_point: [14],
+            }
+        );
+        assert_eq!(
+            indices,
+            Tensor4D {
+                buffer: [matrix![[3, 3], [3, 3]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use core::array;
+
+use libm::{floorf, roundf};
+use nalgebra::convert;
+
+use crate::buffer::Buffer2D;
+use crate::quantize::Quantized;
+use crate::tensor::Tensor4D;
+
+pub enum ResizeMode {
+    NEAREST,
+    BILINEAR,
+}
+
+pub struct Resize2DOptions {
+    pub mode: ResizeMode,
+    pub align_corners: bool,
+}
+
+/// Upsamples/downsamples a `Tensor4D` to `OUTPUT_ROWS`/`OUTPUT_COLS`, for
+/// FPN/segmentation-style feature map resizing between conv blocks.
+pub fn resize_2d<
+    T: Quantized,
+    const INPUT_ROWS: usize,
+    const INPUT_COLS: usize,
+    const CHANS: usize,
+    const OUTPUT_ROWS: usize,
+    const OUTPUT_COLS: usize,
+>(
+    input: Tensor4D<T, 1, INPUT_ROWS, INPUT_COLS, CHANS, 1>,
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+    options: Resize2DOptions,
+) -> Tensor4D<T, 1, OUTPUT_ROWS, OUTPUT_COLS, CHANS, 1> {
+    let map = |o: usize, input_size: usize, output_size: usize| -> f32 {
+        if options.align_corners && output_size > 1 {
+            o as f32 * (input_size - 1) as f32 / (output_size - 1) as f32
+        } else {
+            (0f32).max((o as f32 + 0.5) * input_size as f32 / output_size as f32 - 0.5)
+        }
+    };
+
+    let dequantize = |t: T| -> f32 {
+        input.scale[0] * (convert::<T, i32>(t) - convert::<T, i32>(input.zero_point[0])) as f32
+    };
+    let requantize = |x: f32| -> T {
+        let zero_point = convert::<T, i32>(output_zero_point[0]) as f32;
+        T::from_superset_unchecked(&roundf(x / output_scale[0] + zero_point))
+    };
+
+    Tensor4D::new(
+        [Buffer2D::from_fn(|i, j| {
+            let src_r = map(i, INPUT_ROWS, OUTPUT_ROWS).clamp(0., (INPUT_ROWS - 1) as f32);
+            let src_c = map(j, INPUT_COLS, OUTPUT_COLS).clamp(0., (INPUT_COLS - 1) as f32);
+            array::from_fn(|c| match options.mode {
+                ResizeMode::NEAREST => {
+                    let r = roundf(src_r) as usize;
+                    let col = roundf(src_c) as usize;
+                    let x = input.buffer[0][(r, col)];
+                    requantize(dequantize(x.get(c).copied().unwrap_or(x[0])))
+                }
+                ResizeMode::BILINEAR => {
+                    let r0 = floorf(src_r) as usize;
+                    let c0 = floorf(src_c) as usize;
+                    let r1 = (r0 + 1).min(INPUT_ROWS - 1);
+                    let c1 = (c0 + 1).min(INPUT_COLS - 1);
+                    let fr = src_r - r0 as f32;
+                    let fc = src_c - c0 as f32;
+
+                    let tap = |r: usize, col: usize| -> f32 {
+                        let x = input.buffer[0][(r, col)];
+                        dequantize(x.get(c).copied().unwrap_or(x[0]))
+                    };
+
+                    let top = tap(r0, c0) * (1. - fc) + tap(r0, c1) * fc;
+                    let bottom = tap(r1, c0) * (1. - fc) + tap(r1, c1) * fc;
+                    requantize(top * (1. - fr) + bottom * fr)
+                }
+            })
+        })],
+        output_scale,
+        output_zero_point,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::matrix;
+
+    use super::*;
+
+    const INPUT: Tensor4D<i8, 1, 2, 2, 1, 1> = Tensor4D {
+        buffer: [matrix![
+            [0], [40];
+            [80], [120]
+        ]],
+        scale: [1.],
+        zero_point: [0],
+    };
+
+    #[test]
+    fn resize_2d_bilinear_align_corners() {
+        assert_eq!(
+            resize_2d::<i8, 2, 2, 1, 3, 3>(
+                INPUT,
+                [1.],
+                [0],
+                Resize2DOptions {
+                    mode: ResizeMode::BILINEAR,
+                    align_corners: true,
+                },
+            ),
+            Tensor4D {
+                buffer: [matrix![
+                    [0], [20], [40];
+                    [40], [60], [80];
+                    [80], [100], [120]
+                ]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    #[test]
+    fn resize_2d_nearest_downsample() {
+        assert_eq!(
+            resize_2d::<i8, 2, 2, 1, 1, 1>(
+                INPUT,
+                [1.],
+                [0],
+                Resize2DOptions {
+                    mode: ResizeMode::NEAREST,
+                    align_corners: false,
+                },
+            ),
+            Tensor4D {
+                buffer: [matrix![[120]]],
+                scale: [1.],
+                zero_point: [0],
+            }
+        );
+    }
+
+    #[test]
+    fn resize_2d_nearest_requantizes_across_differing_scale() {
+        assert_eq!(
+            resize_2d::<i8, 2, 2, 1, 1, 1>(
+                INPUT,
+                [2.],
+                [10],
+                Resize2DOptions {
+                    mode: ResizeMode::NEAREST,
+                    align_corners: false,
+                },
+            ),
+            Tensor4D {
+                buffer: [matrix![[70]]],
+                scale: [2.],
+                zero_point: [10],
+            }
+        );
+    }
+}
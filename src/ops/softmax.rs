@@ -0,0 +1,101 @@
+use core::array;
+
+use libm::{expf, roundf};
+use nalgebra::convert;
+
+use crate::buffer::Buffer2D;
+use crate::quantize::Quantized;
+use crate::tensor::Tensor4D;
+
+/// Softmax over the channel axis of a quantized `Tensor4D`.
+pub fn softmax<T: Quantized, const ROWS: usize, const COLS: usize, const CHANS: usize>(
+    input: Tensor4D<T, 1, ROWS, COLS, CHANS, 1>,
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+) -> Tensor4D<T, 1, ROWS, COLS, CHANS, 1> {
+    softmax_impl(input, output_scale, output_zero_point, false)
+}
+
+/// Like [`softmax`], but adds `1` to the denominator so a saturated head can
+/// output an all-near-zero distribution instead of being forced to sum to
+/// one, which improves behavior on out-of-distribution inputs.
+pub fn quiet_softmax<T: Quantized, const ROWS: usize, const COLS: usize, const CHANS: usize>(
+    input: Tensor4D<T, 1, ROWS, COLS, CHANS, 1>,
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+) -> Tensor4D<T, 1, ROWS, COLS, CHANS, 1> {
+    softmax_impl(input, output_scale, output_zero_point, true)
+}
+
+fn softmax_impl<T: Quantized, const ROWS: usize, const COLS: usize, const CHANS: usize>(
+    input: Tensor4D<T, 1, ROWS, COLS, CHANS, 1>,
+    output_scale: [f32; 1],
+    output_zero_point: [T; 1],
+    quiet: bool,
+) -> Tensor4D<T, 1, ROWS, COLS, CHANS, 1> {
+    Tensor4D::new(
+        [Buffer2D::from_fn(|i, j| {
+            let x = input.buffer[0][(i, j)];
+            let dequantized: [f32; CHANS] = array::from_fn(|c| {
+                input.scale[0]
+                    * (convert::<T, i32>(x[c]) - convert::<T, i32>(input.zero_point[0])) as f32
+            });
+            let m = dequantized
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max);
+            let exponentiated: [f32; CHANS] = array::from_fn(|c| expf(dequantized[c] - m));
+            let mut denominator: f32 = exponentiated.iter().sum();
+            if quiet {
+                denominator += 1.;
+            }
+            let zero_point = convert::<T, i32>(output_zero_point[0]) as f32;
+            array::from_fn(|c| {
+                T::from_superset_unchecked(&roundf(
+                    exponentiated[c] / denominator / output_scale[0] + zero_point,
+                ))
+            })
+        })],
+        output_scale,
+        output_zero_point,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::matrix;
+
+    use super::*;
+
+    const INPUT: Tensor4D<i8, 1, 1, 1, 2, 1> = Tensor4D {
+        buffer: [matrix![[0, 0]]],
+        scale: [1.],
+        zero_point: [0],
+    };
+    const OUTPUT_SCALE: [f32; 1] = [0.01];
+    const OUTPUT_ZERO_POINT: [i8; 1] = [0];
+
+    #[test]
+    fn softmax_layer() {
+        assert_eq!(
+            softmax(INPUT, OUTPUT_SCALE, OUTPUT_ZERO_POINT),
+            Tensor4D {
+                buffer: [matrix![[50, 50]]],
+                scale: OUTPUT_SCALE,
+                zero_point: OUTPUT_ZERO_POINT,
+            }
+        );
+    }
+
+    #[test]
+    fn quiet_softmax_layer() {
+        assert_eq!(
+            quiet_softmax(INPUT, OUTPUT_SCALE, OUTPUT_ZERO_POINT),
+            Tensor4D {
+                buffer: [matrix![[33, 33]]],
+                scale: OUTPUT_SCALE,
+                zero_point: OUTPUT_ZERO_POINT,
+            }
+        );
+    }
+}
@@ -0,0 +1,52 @@
+/// 2D padding scheme shared by pooling and convolution operators.
+pub enum Padding2D {
+    SAME,
+    VALID,
+    EXPLICIT {
+        top: usize,
+        bottom: usize,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Padding2D {
+    /// Returns the `(before, after)` padding for the row axis.
+    pub(crate) fn row_offsets(
+        &self,
+        input: usize,
+        filter: usize,
+        stride: usize,
+        output: usize,
+    ) -> (usize, usize) {
+        match self {
+            Padding2D::SAME => Self::same_offsets(input, filter, stride, output),
+            Padding2D::VALID => (0, 0),
+            Padding2D::EXPLICIT { top, bottom, .. } => (*top, *bottom),
+        }
+    }
+
+    /// Returns the `(before, after)` padding for the column axis.
+    pub(crate) fn col_offsets(
+        &self,
+        input: usize,
+        filter: usize,
+        stride: usize,
+        output: usize,
+    ) -> (usize, usize) {
+        match self {
+            Padding2D::SAME => Self::same_offsets(input, filter, stride, output),
+            Padding2D::VALID => (0, 0),
+            Padding2D::EXPLICIT { left, right, .. } => (*left, *right),
+        }
+    }
+
+    /// TFLite/TensorFlow SAME padding: the total padding is split with the
+    /// larger half placed after the input, matching TF's bottom/right bias
+    /// for even filters and strides greater than one.
+    fn same_offsets(input: usize, filter: usize, stride: usize, output: usize) -> (usize, usize) {
+        let pad_total = ((output - 1) * stride + filter).saturating_sub(input);
+        let pad_before = pad_total / 2;
+        (pad_before, pad_total - pad_before)
+    }
+}